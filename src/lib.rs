@@ -1,6 +1,9 @@
 //! Crate that wraps the `sendfile` system call.
 //!
-//! To create a new [`SendFile`] [`Future`] see [`send_file`].
+//! To create a new [`SendFile`] [`Future`] see [`send_file`], or
+//! [`send_file_to`] if the destination isn't necessarily a socket. With the
+//! `io-uring` feature enabled see [`send_file_uring`] for a completion based
+//! alternative on Linux.
 
 #![warn(
     anonymous_parameters,
@@ -22,12 +25,29 @@
 #![doc(test(attr(deny(warnings))))]
 
 use std::future::Future;
-use std::io;
+use std::io::{self, IoSlice};
 use std::marker::Unpin;
-use std::os::unix::io::AsRawFd;
 use std::pin::Pin;
 use std::task::{self, Poll};
 
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, AsRawSocket};
+
+#[cfg(windows)]
+use windows_sys::Win32::System::IO::OVERLAPPED;
+
+#[cfg(feature = "async-io")]
+use async_io::{Async, Writable};
+
+#[cfg(feature = "io-uring")]
+mod uring;
+
+#[cfg(feature = "io-uring")]
+pub use uring::{drive, send_file_uring, SendFileUring};
+
 /// Send a `file` out a `socket`.
 ///
 /// # Arguments
@@ -40,6 +60,12 @@ use std::task::{self, Poll};
 /// [`TcpStream`]: std::net::TcpStream
 /// [`UdpSocket`]: std::net::UdpSocket
 ///
+/// By default the entire `file` is send, starting at the beginning. Use
+/// [`SendFile::offset`] and [`SendFile::limit`] to send only part of the
+/// file, e.g. to serve an HTTP range request. Use [`SendFile::with_headers`]
+/// and [`SendFile::with_trailers`] to send additional data just before and
+/// after the file, e.g. to write an HTTP response around the file.
+///
 /// # Unsafety
 ///
 /// This function is unsafe because the caller must ensure that the provided
@@ -49,7 +75,45 @@ pub unsafe fn send_file<F, S>(file: F, socket: S) -> SendFile<F, S> {
     SendFile {
         file,
         socket,
+        offset: 0,
         written: 0,
+        limit: None,
+        headers: None,
+        headers_written: 0,
+        trailers: None,
+        trailers_written: 0,
+        body_done: false,
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        splice_fallback: false,
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        splice_pipe: None,
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        splice_buffered: 0,
+        #[cfg(windows)]
+        overlapped: None,
+        #[cfg(windows)]
+        pending_transmit: None,
+    }
+}
+
+/// Send `file` out `dst`, which doesn't have to be a socket.
+///
+/// This behaves exactly like [`send_file`], except that on Linux and
+/// Android, where `sendfile`'s destination historically had to be a socket,
+/// a `dst` that returns `EINVAL` (e.g. a pipe or Unix domain socket that
+/// doesn't support `sendfile`) is transparently retried using `splice(2)`
+/// through an internal pipe (`splice` `file` into the pipe, then `splice`
+/// the pipe into `dst`), preserving zero-copy semantics. On other platforms
+/// this is identical to [`send_file`].
+///
+/// # Unsafety
+///
+/// Same requirements as [`send_file`].
+pub unsafe fn send_file_to<F, S>(file: F, dst: S) -> SendFile<F, S> {
+    SendFile {
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        splice_fallback: true,
+        ..send_file(file, dst)
     }
 }
 
@@ -68,17 +132,113 @@ pub unsafe fn send_file<F, S>(file: F, socket: S) -> SendFile<F, S> {
 /// * [FreeBSD](https://www.freebsd.org/cgi/man.cgi?query=sendfile&manpath=FreeBSD+12.0-RELEASE+and+Ports).
 /// * [Linux](http://man7.org/linux/man-pages/man2/sendfile.2.html).
 /// * [macOS](https://developer.apple.com/library/archive/documentation/System/Conceptual/ManPages_iPhoneOS/man2/sendfile.2.html).
+/// * [Windows](https://docs.microsoft.com/en-us/windows/win32/api/mswsock/nf-mswsock-transmitfile),
+///   using `TransmitFile` rather than a `sendfile` system call.
 ///
 /// # Notes
 ///
 /// The [`Future`] implementation doesn't implement waking, it is up to the
 /// caller to ensure future is polled again once the socket is ready to receive
-/// more data.
+/// more data. If the `async-io` feature is enabled [`send_file_async`] can be
+/// used instead, which registers its waker with an [`async-io`] reactor so it
+/// can be used with any executor.
+///
+/// [`async-io`]: async_io
 #[derive(Debug)]
 pub struct SendFile<F, S> {
     file: F,
     socket: S,
+    /// Offset in `file` to start writing from.
+    offset: u64,
+    /// Number of file bytes written so far (relative to `offset`).
     written: usize,
+    /// Maximum number of bytes to write, `None` means write until EOF.
+    limit: Option<u64>,
+    /// Data to write to `socket` before the file, on platforms without
+    /// native support this is written in a separate system call.
+    headers: Option<Vec<u8>>,
+    /// Number of header bytes already written.
+    headers_written: usize,
+    /// Data to write to `socket` after the file, on platforms without
+    /// native support this is written in a separate system call.
+    trailers: Option<Vec<u8>>,
+    /// Number of trailer bytes already written.
+    trailers_written: usize,
+    /// Set once the file has been written in full (or `limit` was reached).
+    body_done: bool,
+    /// Whether or not to fall back to `splice(2)` if `sendfile` returns
+    /// `EINVAL`, set by [`send_file_to`].
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    splice_fallback: bool,
+    /// Pipe used to `splice` `file` into `socket`, created lazily once the
+    /// `splice` fallback is triggered. Owns its file descriptors, closed on
+    /// drop, kept out of `SendFile` itself so that `SendFile` doesn't need a
+    /// `Drop` impl of its own (which would make [`SendFile::into_inner`]'s
+    /// by-value field move a compile error).
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    splice_pipe: Option<SplicePipe>,
+    /// Bytes already read from `file` into `splice_pipe` but not yet written
+    /// to `socket`.
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    splice_buffered: usize,
+    /// `OVERLAPPED` structure of a `TransmitFile` call still in flight, if
+    /// any. Boxed so its address stays stable (the kernel holds a pointer to
+    /// it) independent of where `SendFile` itself lives, and kept around
+    /// across polls instead of being reissued, which would start a second,
+    /// overlapping transfer.
+    #[cfg(windows)]
+    overlapped: Option<Overlapped>,
+    /// `(nbytes, remaining, header_len, trailer_len)` of the `TransmitFile`
+    /// call `overlapped` belongs to, needed to account it once it completes.
+    #[cfg(windows)]
+    pending_transmit: Option<(u32, u64, usize, usize)>,
+}
+
+/// Boxed `OVERLAPPED`, so its address stays stable independent of where
+/// `SendFile` lives, with a placeholder [`std::fmt::Debug`] impl since the
+/// FFI struct (it contains a union) doesn't implement one itself.
+#[cfg(windows)]
+struct Overlapped(Box<OVERLAPPED>);
+
+#[cfg(windows)]
+impl std::fmt::Debug for Overlapped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Overlapped").finish_non_exhaustive()
+    }
+}
+
+/// Owns the non-blocking pipe file descriptors used by the `splice(2)`
+/// fallback, closing both ends on drop.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[derive(Debug)]
+struct SplicePipe {
+    read: std::os::unix::io::RawFd,
+    write: std::os::unix::io::RawFd,
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+impl Drop for SplicePipe {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = libc::close(self.read);
+            let _ = libc::close(self.write);
+        }
+    }
+}
+
+/// Wraps a `&Async<S>` so it implements [`AsRawFd`], since the standard
+/// library doesn't provide a blanket `impl<T: AsRawFd> AsRawFd for &T`.
+/// [`SendFileAsync`] stores `S`'s socket this way so its inner [`SendFile`]
+/// can still call `raw_send_file`, which is bound on `AsRawFd` directly.
+#[cfg(all(feature = "async-io", unix))]
+#[derive(Debug)]
+struct AsyncSocket<'s, S>(&'s Async<S>);
+
+#[cfg(all(feature = "async-io", unix))]
+impl<S: AsRawFd> AsRawFd for AsyncSocket<'_, S> {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.0.as_raw_fd()
+    }
 }
 
 impl<F, S> SendFile<F, S> {
@@ -87,81 +247,411 @@ impl<F, S> SendFile<F, S> {
         (self.file, self.socket)
     }
 
-    /// Returns the number of bytes written.
+    /// Returns the number of bytes written, including headers and trailers
+    /// set with [`SendFile::with_headers`] and [`SendFile::with_trailers`].
     pub fn written(&self) -> usize {
-        self.written
+        self.headers_written + self.written + self.trailers_written
     }
+
+    /// Start writing `file` from `start`, instead of from the beginning of
+    /// the file.
+    ///
+    /// # Notes
+    ///
+    /// Must be called before the future is polled for the first time.
+    pub fn offset(mut self, start: u64) -> Self {
+        self.offset = start;
+        self
+    }
+
+    /// Limit the number of bytes written to `max`, useful for sending only
+    /// part of `file`, e.g. to serve an HTTP range request.
+    ///
+    /// # Notes
+    ///
+    /// Must be called before the future is polled for the first time.
+    pub fn limit(mut self, max: u64) -> Self {
+        self.limit = Some(max);
+        self
+    }
+
+    /// Write `headers` to the socket just before the file, e.g. the status
+    /// line and headers of an HTTP response.
+    ///
+    /// On FreeBSD and macOS this uses the `sendfile` system call's native
+    /// `sf_hdtr` support to write the header together with (the start of)
+    /// the file. On Linux and Android, which don't have this support, the
+    /// header is written in a separate `writev` call before the first
+    /// `sendfile` call.
+    ///
+    /// # Notes
+    ///
+    /// Must be called before the future is polled for the first time.
+    pub fn with_headers(mut self, headers: &[IoSlice<'_>]) -> Self {
+        self.headers = Some(concat_slices(headers));
+        self
+    }
+
+    /// Write `trailers` to the socket just after the file, e.g. a chunked
+    /// transfer-encoding trailer.
+    ///
+    /// See [`SendFile::with_headers`] for platform support.
+    ///
+    /// # Notes
+    ///
+    /// Must be called before the future is polled for the first time.
+    pub fn with_trailers(mut self, trailers: &[IoSlice<'_>]) -> Self {
+        self.trailers = Some(concat_slices(trailers));
+        self
+    }
+
+    /// Whether or not the headers, file and trailers have all been written.
+    fn is_complete(&self) -> bool {
+        self.body_done
+            && self.headers_written == self.headers.as_ref().map_or(0, Vec::len)
+            && self.trailers_written == self.trailers.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Number of bytes left to write, taking `limit` into account.
+    fn remaining(&self) -> u64 {
+        match self.limit {
+            Some(limit) => limit - self.written as u64,
+            None => u64::MAX,
+        }
+    }
+}
+
+/// Concatenate `slices` into a single owned buffer.
+fn concat_slices(slices: &[IoSlice<'_>]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(slices.iter().map(|slice| slice.len()).sum());
+    for slice in slices {
+        buf.extend_from_slice(slice);
+    }
+    buf
 }
 
+#[cfg(unix)]
 impl<F, S> SendFile<F, S>
 where
     F: AsRawFd,
     S: AsRawFd,
 {
+    /// Build the header/trailer iovecs for the native `sf_hdtr` struct, if
+    /// applicable to this call.
+    ///
+    /// The header, sliced from `headers_written` so a short/`EAGAIN`'d write
+    /// resumes mid-header instead of re-sending it whole; the trailer is
+    /// attached, similarly sliced from `trailers_written`, once
+    /// [`SendFile::is_complete`]'s `body_done` condition holds, i.e. once the
+    /// file itself has been written in full.
+    #[cfg(any(target_os = "freebsd", target_os = "macos"))]
+    fn hdtr_iovecs(&self) -> (Option<libc::iovec>, Option<libc::iovec>) {
+        let header = self.headers.as_ref().and_then(|headers| {
+            (self.headers_written < headers.len()).then(|| libc::iovec {
+                iov_base: headers[self.headers_written..].as_ptr() as *mut _,
+                iov_len: headers.len() - self.headers_written,
+            })
+        });
+        let trailer = if self.body_done {
+            self.trailers.as_ref().and_then(|trailers| {
+                (self.trailers_written < trailers.len()).then(|| libc::iovec {
+                    iov_base: trailers[self.trailers_written..].as_ptr() as *mut _,
+                    iov_len: trailers.len() - self.trailers_written,
+                })
+            })
+        } else {
+            None
+        };
+        (header, trailer)
+    }
+
     #[cfg(target_os = "macos")]
     fn raw_send_file(&mut self) -> io::Result<usize> {
         let file = self.file.as_raw_fd();
         let socket = self.socket.as_raw_fd();
-        // On macOS `length` is value-result parameter. It determines the number
-        // of bytes to write and returns the number of bytes written also in
-        // case of `EAGAIN` errors.
-        let mut length = 0; // Send all bytes.
-        let res = unsafe {
-            libc::sendfile(
-                file,
+        let offset = self.offset + self.written as u64;
+
+        let (header, trailer) = self.hdtr_iovecs();
+        let mut hdtr = libc::sf_hdtr {
+            headers: header
+                .as_ref()
+                .map_or(std::ptr::null_mut(), |iov| iov as *const _ as *mut _),
+            hdr_cnt: header.is_some() as libc::c_int,
+            trailers: trailer
+                .as_ref()
+                .map_or(std::ptr::null_mut(), |iov| iov as *const _ as *mut _),
+            trl_cnt: trailer.is_some() as libc::c_int,
+        };
+        let hdtr_ptr = if header.is_some() || trailer.is_some() {
+            &mut hdtr as *mut _
+        } else {
+            std::ptr::null_mut()
+        };
+
+        // On macOS `length` is a value-result parameter. It determines the
+        // number of file bytes to write and, including in case of `EAGAIN`
+        // errors, returns the total number of header, file and trailer bytes
+        // actually written (in that order). It must be accounted *before*
+        // checking `res`, otherwise a short write on `WouldBlock` discards
+        // bytes already in the socket buffer and the next call re-sends them
+        // from a stale offset.
+        let mut length = if self.body_done {
+            0
+        } else {
+            match self.limit {
+                Some(_) => self.remaining() as libc::off_t,
+                None => 0, // Send all bytes.
+            }
+        };
+        let res =
+            unsafe { libc::sendfile(file, socket, offset as libc::off_t, &mut length, hdtr_ptr, 0) };
+
+        // Distribute the bytes actually sent across header, file and
+        // trailer, in the order the kernel writes them, so a partial write
+        // resumes mid-header/mid-trailer rather than re-sending it whole.
+        let mut sent = length as usize;
+        if let Some(header) = header {
+            let n = sent.min(header.iov_len);
+            self.headers_written += n;
+            sent -= n;
+        }
+        if !self.body_done {
+            self.written += sent;
+            let limit_reached = self.limit == Some(self.written as u64);
+            let eof_reached = res != -1 && sent == 0;
+            if limit_reached || eof_reached {
+                self.body_done = true;
+            }
+            sent = 0;
+        }
+        if let Some(trailer) = trailer {
+            let n = sent.min(trailer.iov_len);
+            self.trailers_written += n;
+        }
+
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(length as usize)
+    }
+
+    /// Move up to `count` bytes from `self.file` to `self.socket` through the
+    /// pipe in `self.splice_pipe`, for use when `self.socket` isn't a socket
+    /// `sendfile` can write to directly (see `splice_fallback`).
+    ///
+    /// Bytes read from `file` but not yet flushed to `socket` are tracked in
+    /// `splice_buffered`, so a short write to `socket` doesn't lose data
+    /// sitting in the pipe.
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    fn splice_send(&mut self, count: usize) -> io::Result<usize> {
+        if self.splice_buffered == 0 {
+            let file = self.file.as_raw_fd();
+            let pipe_write = self.splice_pipe.as_ref().unwrap().write;
+            let mut offset = (self.offset + self.written as u64) as libc::off_t;
+            let n = unsafe {
+                libc::splice(
+                    file,
+                    &mut offset,
+                    pipe_write,
+                    std::ptr::null_mut(),
+                    count,
+                    libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK,
+                )
+            };
+            if n == -1 {
+                return Err(io::Error::last_os_error());
+            } else if n == 0 {
+                self.body_done = true;
+                return Ok(0);
+            }
+            self.splice_buffered = n as usize;
+        }
+
+        let pipe_read = self.splice_pipe.as_ref().unwrap().read;
+        let socket = self.socket.as_raw_fd();
+        let n = unsafe {
+            libc::splice(
+                pipe_read,
+                std::ptr::null_mut(),
                 socket,
-                self.written as libc::off_t,
-                &mut length,
                 std::ptr::null_mut(),
-                0,
+                self.splice_buffered,
+                libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK,
             )
         };
-        self.written += length as usize;
-        if res == -1 {
-            Err(io::Error::last_os_error())
-        } else {
-            Ok(length as usize)
+        if n == -1 {
+            return Err(io::Error::last_os_error());
         }
+        let n = n as usize;
+        self.splice_buffered -= n;
+        self.written += n;
+        if self.limit == Some(self.written as u64) {
+            self.body_done = true;
+        }
+        Ok(n)
     }
 
     #[cfg(any(target_os = "android", target_os = "linux"))]
     fn raw_send_file(&mut self) -> io::Result<usize> {
-        let file = self.file.as_raw_fd();
         let socket = self.socket.as_raw_fd();
-        // This is the maximum the Linux kernel will write in a single call.
-        let count = 0x7ffff000;
-        let mut offset = self.written as libc::off_t;
-        let n = unsafe { libc::sendfile(socket, file, &mut offset, count) };
-        if n == -1 {
-            Err(io::Error::last_os_error())
-        } else {
-            self.written = offset as usize;
-            Ok(n as usize)
+
+        if let Some(headers) = &self.headers {
+            if self.headers_written < headers.len() {
+                let n = write_iovec(socket, &headers[self.headers_written..])?;
+                self.headers_written += n;
+                return Ok(n);
+            }
         }
+
+        if !self.body_done {
+            // This is the maximum the Linux kernel will write in a single call.
+            let max_count: u64 = 0x7ffff000;
+            let count = std::cmp::min(max_count, self.remaining()) as usize;
+
+            if self.splice_pipe.is_some() {
+                return self.splice_send(count);
+            }
+
+            let file = self.file.as_raw_fd();
+            let mut offset = (self.offset + self.written as u64) as libc::off_t;
+            let n = unsafe { libc::sendfile(socket, file, &mut offset, count) };
+            if n == -1 {
+                let err = io::Error::last_os_error();
+                if self.splice_fallback && err.raw_os_error() == Some(libc::EINVAL) {
+                    // `socket` isn't a socket `sendfile` can write to, e.g.
+                    // a pipe or Unix domain socket; fall back to `splice`.
+                    self.splice_pipe = Some(make_splice_pipe()?);
+                    return self.raw_send_file();
+                }
+                return Err(err);
+            }
+            self.written = (offset as u64 - self.offset) as usize;
+            if n == 0 || self.limit == Some(self.written as u64) {
+                self.body_done = true;
+            }
+            return Ok(n as usize);
+        }
+
+        if let Some(trailers) = &self.trailers {
+            if self.trailers_written < trailers.len() {
+                let n = write_iovec(socket, &trailers[self.trailers_written..])?;
+                self.trailers_written += n;
+                return Ok(n);
+            }
+        }
+
+        Ok(0)
     }
 
     #[cfg(target_os = "freebsd")]
     fn raw_send_file(&mut self) -> io::Result<usize> {
         let file = self.file.as_raw_fd();
         let socket = self.socket.as_raw_fd();
+        let offset = self.offset + self.written as u64;
+
+        let (header, trailer) = self.hdtr_iovecs();
+        let mut hdtr = libc::sf_hdtr {
+            headers: header
+                .as_ref()
+                .map_or(std::ptr::null_mut(), |iov| iov as *const _ as *mut _),
+            hdr_cnt: header.is_some() as libc::c_int,
+            trailers: trailer
+                .as_ref()
+                .map_or(std::ptr::null_mut(), |iov| iov as *const _ as *mut _),
+            trl_cnt: trailer.is_some() as libc::c_int,
+        };
+        let hdtr_ptr = if header.is_some() || trailer.is_some() {
+            &mut hdtr as *mut _
+        } else {
+            std::ptr::null_mut()
+        };
+
+        let nbytes = if self.body_done {
+            0
+        } else {
+            match self.limit {
+                Some(_) => self.remaining() as libc::off_t,
+                None => 0, // Send until EOF.
+            }
+        };
+        // `bytes_sent` is a value-result parameter: on return -- including
+        // on `EAGAIN` -- it holds the total number of header, file and
+        // trailer bytes actually written (in that order). It must be
+        // accounted *before* checking `res`, otherwise a short write on
+        // `WouldBlock` discards bytes already in the socket buffer and the
+        // next call re-sends them from a stale offset.
         let mut bytes_sent = 0;
         let res = unsafe {
             libc::sendfile(
                 file,
                 socket,
-                self.written as libc::off_t,
-                0,
-                std::ptr::null_mut(),
+                offset as libc::off_t,
+                nbytes,
+                hdtr_ptr,
                 &mut bytes_sent,
                 0,
             )
         };
-        self.written += bytes_sent as usize;
+
+        // Distribute the bytes actually sent across header, file and
+        // trailer, in the order the kernel writes them, so a partial write
+        // resumes mid-header/mid-trailer rather than re-sending it whole.
+        let mut sent = bytes_sent as usize;
+        if let Some(header) = header {
+            let n = sent.min(header.iov_len);
+            self.headers_written += n;
+            sent -= n;
+        }
+        if !self.body_done {
+            self.written += sent;
+            let limit_reached = self.limit == Some(self.written as u64);
+            let eof_reached = res != -1 && sent == 0;
+            if limit_reached || eof_reached {
+                self.body_done = true;
+            }
+            sent = 0;
+        }
+        if let Some(trailer) = trailer {
+            let n = sent.min(trailer.iov_len);
+            self.trailers_written += n;
+        }
+
         if res == -1 {
-            Err(io::Error::last_os_error())
-        } else {
-            Ok(bytes_sent as usize)
+            return Err(io::Error::last_os_error());
         }
+        Ok(bytes_sent as usize)
+    }
+}
+
+/// Write `buf` to `socket` in a single `writev` call, used to emulate
+/// headers/trailers on platforms without native `sf_hdtr` support.
+#[cfg(unix)]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn write_iovec(socket: std::os::unix::io::RawFd, buf: &[u8]) -> io::Result<usize> {
+    let iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut _,
+        iov_len: buf.len(),
+    };
+    let n = unsafe { libc::writev(socket, &iov, 1) };
+    if n == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
+
+/// Create the non-blocking pipe used to `splice` between `file` and `socket`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn make_splice_pipe() -> io::Result<SplicePipe> {
+    let mut fds = [0; 2];
+    let res = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) };
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(SplicePipe {
+            read: fds[0],
+            write: fds[1],
+        })
     }
 }
 
@@ -181,8 +671,194 @@ where
 
     fn poll(mut self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
         loop {
+            if self.limit == Some(self.written as u64) {
+                self.body_done = true;
+            }
+            if self.is_complete() {
+                break Poll::Ready(Ok(()));
+            }
+            match self.raw_send_file() {
+                Ok(_) => continue, // Attempt to write some more bytes.
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => break Poll::Pending,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue, // Try again.
+                    Err(err) => break Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl<F, S> SendFile<F, S>
+where
+    F: AsRawHandle,
+    S: AsRawSocket,
+{
+    /// Build the `Head`/`Tail` buffers for the native `TRANSMIT_FILE_BUFFERS`
+    /// struct, if applicable to this call.
+    ///
+    /// Unlike the Unix `sf_hdtr` backends `TransmitFile` writes the header,
+    /// file and trailer in a single call, so the header is attached until
+    /// we've recorded it as written. The trailer must only go out with
+    /// `final_chunk`: attaching it earlier would emit it after whatever
+    /// part of the file this call happens to cover, not after all of it.
+    fn hdtr_buffers(&self, final_chunk: bool) -> (Option<&[u8]>, Option<&[u8]>) {
+        let header = self
+            .headers
+            .as_deref()
+            .filter(|_| self.headers_written == 0);
+        let trailer = self
+            .trailers
+            .as_deref()
+            .filter(|_| final_chunk && self.trailers_written == 0);
+        (header, trailer)
+    }
+
+    fn raw_send_file(&mut self) -> io::Result<usize> {
+        use std::mem;
+        use windows_sys::Win32::Foundation::{
+            GetLastError, ERROR_IO_INCOMPLETE, ERROR_IO_PENDING,
+        };
+        use windows_sys::Win32::Networking::WinSock::{
+            TransmitFile, TRANSMIT_FILE_BUFFERS, TF_USE_DEFAULT_WORKER,
+        };
+        use windows_sys::Win32::Storage::FileSystem::GetFileSizeEx;
+        use windows_sys::Win32::System::IO::GetOverlappedResult;
+
+        let socket = self.socket.as_raw_socket();
+
+        // A `TransmitFile` call is already running: poll it for completion
+        // instead of issuing a second one. The kernel still holds a pointer
+        // into `overlapped`, which must stay alive until the operation
+        // completes, and reissuing `TransmitFile` from the same offset would
+        // duplicate the transfer.
+        if let Some(overlapped) = &mut self.overlapped {
+            let mut transferred = 0;
+            let ok = unsafe {
+                GetOverlappedResult(socket as _, &mut *overlapped.0, &mut transferred, 0)
+            };
+            if ok == 0 {
+                let err = unsafe { GetLastError() };
+                if err == ERROR_IO_INCOMPLETE {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                self.overlapped = None;
+                self.pending_transmit = None;
+                return Err(io::Error::from_raw_os_error(err as i32));
+            }
+            let (nbytes, remaining, header_len, trailer_len) =
+                self.pending_transmit.take().unwrap();
+            self.overlapped = None;
+            return Ok(self.finish_transmit(nbytes, remaining, header_len, trailer_len));
+        }
+
+        let file = self.file.as_raw_handle();
+        let offset = self.offset + self.written as u64;
+
+        let mut file_size = 0i64;
+        if unsafe { GetFileSizeEx(file as _, &mut file_size) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let remaining_file = (file_size as u64).saturating_sub(offset);
+        let remaining = match self.limit {
+            Some(_) => self.remaining().min(remaining_file),
+            None => remaining_file,
+        };
+        let final_chunk = remaining <= u32::MAX as u64;
+        let nbytes = remaining.min(u32::MAX as u64) as u32;
+
+        let (header, trailer) = self.hdtr_buffers(final_chunk);
+        let header_len = header.map_or(0, <[u8]>::len);
+        let trailer_len = trailer.map_or(0, <[u8]>::len);
+        let mut buffers = TRANSMIT_FILE_BUFFERS {
+            Head: header.map_or(std::ptr::null_mut(), |h| h.as_ptr() as *mut _),
+            HeadLength: header_len as u32,
+            Tail: trailer.map_or(std::ptr::null_mut(), |t| t.as_ptr() as *mut _),
+            TailLength: trailer_len as u32,
+        };
+        let buffers_ptr = if header.is_some() || trailer.is_some() {
+            &mut buffers as *mut _
+        } else {
+            std::ptr::null_mut()
+        };
+
+        let mut overlapped = Overlapped(Box::new(unsafe { mem::zeroed() }));
+        overlapped.0.Anonymous.Anonymous.Offset = offset as u32;
+        overlapped.0.Anonymous.Anonymous.OffsetHigh = (offset >> 32) as u32;
+
+        let ok = unsafe {
+            TransmitFile(
+                socket as _,
+                file as _,
+                nbytes,
+                0,
+                &mut *overlapped.0,
+                buffers_ptr,
+                TF_USE_DEFAULT_WORKER,
+            )
+        };
+        if ok == 0 {
+            let err = unsafe { GetLastError() };
+            if err == ERROR_IO_PENDING {
+                // The transfer is running asynchronously, report it the same
+                // way the Unix backends report `EWOULDBLOCK`: the caller
+                // polls us again once the socket is ready. Keep `overlapped`
+                // alive and remember the sizes needed to account it once a
+                // later poll observes its completion.
+                self.overlapped = Some(overlapped);
+                self.pending_transmit = Some((nbytes, remaining, header_len, trailer_len));
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            return Err(io::Error::from_raw_os_error(err as i32));
+        }
+
+        Ok(self.finish_transmit(nbytes, remaining, header_len, trailer_len))
+    }
+
+    /// Account a `TransmitFile` call that completed, whether synchronously
+    /// or overlapped, and return the total number of bytes it wrote.
+    ///
+    /// `TransmitFile` doesn't do short writes, on success it has written
+    /// everything it was asked to.
+    fn finish_transmit(
+        &mut self,
+        nbytes: u32,
+        remaining: u64,
+        header_len: usize,
+        trailer_len: usize,
+    ) -> usize {
+        if header_len > 0 {
+            self.headers_written = header_len;
+        }
+        if trailer_len > 0 {
+            self.trailers_written = trailer_len;
+        }
+        self.written += nbytes as usize;
+        if remaining == nbytes as u64 {
+            self.body_done = true;
+        }
+        nbytes as usize + header_len + trailer_len
+    }
+}
+
+#[cfg(windows)]
+impl<F, S> Future for SendFile<F, S>
+where
+    F: AsRawHandle + Unpin,
+    S: AsRawSocket + Unpin,
+{
+    /// `Ok(())` once everything is written, or an I/O error. See
+    /// [`SendFile::written`] for the number of bytes written.
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut task::Context<'_>) -> Poll<Self::Output> {
+        loop {
+            if self.limit == Some(self.written as u64) {
+                self.body_done = true;
+            }
+            if self.is_complete() {
+                break Poll::Ready(Ok(()));
+            }
             match self.raw_send_file() {
-                Ok(0) => break Poll::Ready(Ok(())),
                 Ok(_) => continue, // Attempt to write some more bytes.
                 Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => break Poll::Pending,
                 Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue, // Try again.
@@ -191,3 +867,147 @@ where
         }
     }
 }
+
+/// Send a `file` out a `socket`, waking the task once the socket becomes
+/// writable.
+///
+/// Unlike [`send_file`] the returned [`SendFileAsync`] future properly
+/// implements waking: when the `sendfile` system call returns
+/// [`WouldBlock`], `socket`'s file descriptor is registered with the
+/// [`async-io`] reactor and the task is woken once it's writable again, so
+/// the future can be used with any executor instead of being polled in a
+/// loop.
+///
+/// [`WouldBlock`]: io::ErrorKind::WouldBlock
+/// [`async-io`]: async_io
+///
+/// # Unsafety
+///
+/// Same requirements as [`send_file`].
+#[cfg(feature = "async-io")]
+pub unsafe fn send_file_async<F, S>(file: F, socket: &Async<S>) -> SendFileAsync<'_, F, S>
+where
+    F: AsRawFd,
+    S: AsRawFd,
+{
+    SendFileAsync {
+        inner: SendFile {
+            file,
+            socket: AsyncSocket(socket),
+            offset: 0,
+            written: 0,
+            limit: None,
+            headers: None,
+            headers_written: 0,
+            trailers: None,
+            trailers_written: 0,
+            body_done: false,
+            #[cfg(any(target_os = "android", target_os = "linux"))]
+            splice_fallback: false,
+            #[cfg(any(target_os = "android", target_os = "linux"))]
+            splice_pipe: None,
+            #[cfg(any(target_os = "android", target_os = "linux"))]
+            splice_buffered: 0,
+            #[cfg(windows)]
+            overlapped: None,
+            #[cfg(windows)]
+            pending_transmit: None,
+        },
+        writable: None,
+    }
+}
+
+/// [`Future`] returned by [`send_file_async`].
+#[cfg(feature = "async-io")]
+#[derive(Debug)]
+pub struct SendFileAsync<'s, F, S> {
+    inner: SendFile<F, AsyncSocket<'s, S>>,
+    writable: Option<Writable<'s, S>>,
+}
+
+#[cfg(feature = "async-io")]
+impl<'s, F, S> SendFileAsync<'s, F, S> {
+    /// Retrieve the file and socket.
+    pub fn into_inner(self) -> (F, &'s Async<S>) {
+        let (file, socket) = self.inner.into_inner();
+        (file, socket.0)
+    }
+
+    /// Returns the number of bytes written.
+    pub fn written(&self) -> usize {
+        self.inner.written()
+    }
+
+    /// Start writing the file from `start`, see [`SendFile::offset`].
+    pub fn offset(mut self, start: u64) -> Self {
+        self.inner = self.inner.offset(start);
+        self
+    }
+
+    /// Limit the number of bytes written to `max`, see [`SendFile::limit`].
+    pub fn limit(mut self, max: u64) -> Self {
+        self.inner = self.inner.limit(max);
+        self
+    }
+
+    /// Write `headers` before the file, see [`SendFile::with_headers`].
+    pub fn with_headers(mut self, headers: &[IoSlice<'_>]) -> Self {
+        self.inner = self.inner.with_headers(headers);
+        self
+    }
+
+    /// Write `trailers` after the file, see [`SendFile::with_trailers`].
+    pub fn with_trailers(mut self, trailers: &[IoSlice<'_>]) -> Self {
+        self.inner = self.inner.with_trailers(trailers);
+        self
+    }
+}
+
+#[cfg(all(
+    feature = "async-io",
+    any(
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "linux",
+        target_os = "macos",
+    )
+))]
+impl<F, S> Future for SendFileAsync<'_, F, S>
+where
+    F: AsRawFd + Unpin,
+    S: AsRawFd,
+{
+    /// `Ok(())` once everything is written, or an I/O error. See
+    /// [`SendFileAsync::written`] for the number of bytes written.
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            if this.inner.limit == Some(this.inner.written as u64) {
+                this.inner.body_done = true;
+            }
+            if this.inner.is_complete() {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(writable) = this.writable.as_mut() {
+                match Pin::new(writable).poll(cx) {
+                    Poll::Ready(Ok(())) => this.writable = None,
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            match this.inner.raw_send_file() {
+                Ok(_) => continue, // Attempt to write some more bytes.
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    // Register our waker with the reactor and retry once
+                    // `socket` becomes writable.
+                    this.writable = Some(this.inner.socket.0.writable());
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue, // Try again.
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! [`io_uring`] completion-based backend, enabled with the `io-uring`
+//! feature.
+//!
+//! Unlike [`crate::SendFile`] this doesn't poll a readiness based `sendfile`
+//! loop. Instead it submits a `file` -> pipe -> `socket` splice chain to a
+//! shared [`IoUring`] and resolves once the kernel signals completion,
+//! avoiding a system call per wakeup.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::marker::Unpin;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{self, Poll, Waker};
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+/// Maximum number of bytes spliced in a single chunk.
+const CHUNK_SIZE: usize = 1 << 20; // 1 MiB.
+
+/// Send `file` out `socket`, completing via `ring`'s completion queue rather
+/// than readiness polling.
+///
+/// This creates an internal pipe used to `splice` `file` into it and then
+/// `splice` the pipe into `socket`, submitting both operations to `ring` as
+/// a single linked chain (`IORING_OP_SPLICE`). A short completion is
+/// resubmitted from the updated [`SendFileUring::written`] offset.
+///
+/// The caller owns `ring` and is responsible for calling [`drive`] whenever
+/// `ring`'s completion queue may have new entries (e.g. after `ring`'s file
+/// descriptor becomes readable), so that outstanding [`SendFileUring`]
+/// futures are woken.
+///
+/// # Unsafety
+///
+/// Same requirements as [`send_file`](crate::send_file). In addition `ring`'s
+/// submission and completion queues are accessed without synchronisation, so
+/// the returned future and [`drive`] must all be polled/called from a single
+/// thread.
+pub unsafe fn send_file_uring<F, S>(
+    file: F,
+    socket: S,
+    ring: &IoUring,
+) -> io::Result<SendFileUring<'_, F, S>>
+where
+    F: AsRawFd,
+    S: AsRawFd,
+{
+    let mut fds = [0; 2];
+    if libc::pipe(fds.as_mut_ptr()) == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(SendFileUring {
+        ring,
+        file,
+        socket,
+        pipe_read: fds[0],
+        pipe_write: fds[1],
+        written: 0,
+        op: None,
+        op_user_data: None,
+    })
+}
+
+/// [`Future`] returned by [`send_file_uring`].
+pub struct SendFileUring<'r, F, S> {
+    ring: &'r IoUring,
+    file: F,
+    socket: S,
+    pipe_read: RawFd,
+    pipe_write: RawFd,
+    written: usize,
+    /// The splice chain currently submitted to `ring`, if any.
+    op: Option<Arc<Mutex<Completion>>>,
+    /// `user_data` of `op`'s `pipe` -> `socket` splice entry, its key in
+    /// [`completions`].
+    op_user_data: Option<u64>,
+}
+
+/// `IoUring` doesn't implement [`std::fmt::Debug`], so skip `ring`.
+impl<F: std::fmt::Debug, S: std::fmt::Debug> std::fmt::Debug for SendFileUring<'_, F, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendFileUring")
+            .field("file", &self.file)
+            .field("socket", &self.socket)
+            .field("pipe_read", &self.pipe_read)
+            .field("pipe_write", &self.pipe_write)
+            .field("written", &self.written)
+            .field("op", &self.op)
+            .field("op_user_data", &self.op_user_data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, S> SendFileUring<'_, F, S> {
+    /// Returns the number of bytes written.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<F, S> Drop for SendFileUring<'_, F, S> {
+    fn drop(&mut self) {
+        if let Some(user_data) = self.op_user_data.take() {
+            // Make sure the kernel is actually done with `pipe_read` and
+            // `pipe_write` before closing them -- otherwise a splice chain
+            // still in flight could complete into closed (and potentially
+            // reused) file descriptors.
+            let still_pending = completions()
+                .lock()
+                .unwrap()
+                .get(&user_data)
+                .map_or(false, |completion| completion.lock().unwrap().result.is_none());
+            if still_pending {
+                cancel_and_drain(self.ring, user_data);
+            } else {
+                let _ = completions().lock().unwrap().remove(&user_data);
+            }
+        }
+
+        unsafe {
+            let _ = libc::close(self.pipe_read);
+            let _ = libc::close(self.pipe_write);
+        }
+    }
+}
+
+/// Ask `ring` to cancel the splice chain keyed by `user_data` and block
+/// until its completion (cancelled or not) has been observed and removed
+/// from [`completions`].
+fn cancel_and_drain(ring: &IoUring, user_data: u64) {
+    let cancel = opcode::AsyncCancel::new(user_data)
+        .build()
+        .user_data(0); // Only the entry being cancelled is observed.
+    unsafe {
+        let mut sq = ring.submission_shared();
+        let _ = sq.push(&cancel);
+    }
+    let _ = ring.submit();
+
+    loop {
+        {
+            let mut cq = unsafe { ring.completion_shared() };
+            cq.sync();
+            for cqe in &mut cq {
+                if cqe.user_data() == user_data {
+                    let _ = completions().lock().unwrap().remove(&user_data);
+                }
+            }
+        }
+        if !completions().lock().unwrap().contains_key(&user_data) {
+            break;
+        }
+        std::thread::yield_now();
+    }
+}
+
+/// Result of a single submitted splice chain, shared between the future and
+/// [`drive`] via the [`completions`] registry.
+#[derive(Debug, Default)]
+struct Completion {
+    waker: Option<Waker>,
+    /// `cqe.result()` of the `pipe` -> `socket` splice, once it arrives.
+    result: Option<i32>,
+}
+
+/// Outstanding completions, keyed by the `user_data` of their `pipe` ->
+/// `socket` splice entry.
+fn completions() -> &'static Mutex<HashMap<u64, Arc<Mutex<Completion>>>> {
+    static COMPLETIONS: OnceLock<Mutex<HashMap<u64, Arc<Mutex<Completion>>>>> = OnceLock::new();
+    COMPLETIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Generate a `user_data` value unique to this process.
+fn next_user_data() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Drive completions for outstanding [`SendFileUring`] futures submitted to
+/// `ring`, waking any tasks whose splice chain has finished.
+///
+/// Call this from the executor's event loop whenever `ring` may have new
+/// completion queue entries.
+pub fn drive(ring: &IoUring) {
+    let mut completions = completions().lock().unwrap();
+    let mut cq = unsafe { ring.completion_shared() };
+    cq.sync();
+    for cqe in &mut cq {
+        if let Some(completion) = completions.remove(&cqe.user_data()) {
+            let mut completion = completion.lock().unwrap();
+            completion.result = Some(cqe.result());
+            if let Some(waker) = completion.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<F, S> Future for SendFileUring<'_, F, S>
+where
+    F: AsRawFd + Unpin,
+    S: AsRawFd + Unpin,
+{
+    /// The number of bytes written, or an I/O error.
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(op) = &this.op {
+            let mut op = op.lock().unwrap();
+            match op.result {
+                None => {
+                    op.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                Some(res) if res < 0 => {
+                    drop(op);
+                    this.op = None;
+                    this.op_user_data = None;
+                    return Poll::Ready(Err(io::Error::from_raw_os_error(-res)));
+                }
+                Some(0) => {
+                    drop(op);
+                    this.op = None;
+                    this.op_user_data = None;
+                    return Poll::Ready(Ok(this.written));
+                }
+                Some(n) => {
+                    this.written += n as usize;
+                    drop(op);
+                    this.op = None; // Submit the next chunk below.
+                    this.op_user_data = None;
+                }
+            }
+        }
+
+        let user_data = next_user_data();
+        let completion = Arc::new(Mutex::new(Completion {
+            waker: Some(cx.waker().clone()),
+            result: None,
+        }));
+        let _ = completions()
+            .lock()
+            .unwrap()
+            .insert(user_data, Arc::clone(&completion));
+
+        let file_to_pipe = opcode::Splice::new(
+            types::Fd(this.file.as_raw_fd()),
+            this.written as i64,
+            types::Fd(this.pipe_write),
+            -1,
+            CHUNK_SIZE as u32,
+        )
+        .build()
+        .user_data(0) // Only the final entry's completion is observed.
+        .flags(squeue::Flags::IO_LINK);
+        let pipe_to_socket = opcode::Splice::new(
+            types::Fd(this.pipe_read),
+            -1,
+            types::Fd(this.socket.as_raw_fd()),
+            -1,
+            CHUNK_SIZE as u32,
+        )
+        .build()
+        .user_data(user_data);
+
+        // Safety: `ring`'s submission queue is only shared across `poll`
+        // calls driven by a single executor thread, as documented on
+        // `send_file_uring`.
+        unsafe {
+            let mut sq = this.ring.submission_shared();
+            let _ = sq.push(&file_to_pipe);
+            let _ = sq.push(&pipe_to_socket);
+        }
+        if let Err(err) = this.ring.submit() {
+            let _ = completions().lock().unwrap().remove(&user_data);
+            return Poll::Ready(Err(err));
+        }
+
+        this.op = Some(completion);
+        this.op_user_data = Some(user_data);
+        Poll::Pending
+    }
+}
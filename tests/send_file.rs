@@ -1,6 +1,6 @@
 use std::fs::File;
 use std::future::Future;
-use std::io::{self, Read};
+use std::io::{self, IoSlice, Read};
 use std::marker::Unpin;
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::pin::Pin;
@@ -93,6 +93,125 @@ fn tcp_blocking_non_blocking() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn tcp_offset_and_limit() -> io::Result<()> {
+    let mut ctx = noop_context();
+    let test = &TEST_FILES[TEST_FILES.len() - 1];
+    let server = tcp_server(1)?;
+
+    let start: u64 = 10;
+    let len: u64 = 100;
+    let file = File::open(test.path)?;
+    let stream = TcpStream::connect(server.address)?;
+
+    let mut send_file = unsafe { send_file(file, stream) }.offset(start).limit(len);
+
+    let result = Pin::new(&mut send_file).poll(&mut ctx)?;
+    assert!(result.is_ready());
+    assert_eq!(send_file.written(), len as usize);
+
+    let (_, socket) = send_file.into_inner();
+    drop(socket); // Close the socket.
+
+    let (_, data) = server.send_files.recv().unwrap();
+    assert_eq!(data, test.data[start as usize..(start + len) as usize]);
+
+    Ok(())
+}
+
+#[test]
+fn tcp_headers_and_trailers() -> io::Result<()> {
+    let mut ctx = noop_context();
+    let test = &TEST_FILES[1];
+    let server = tcp_server(1)?;
+
+    let header = b"HEADER\n";
+    let trailer = b"TRAILER\n";
+
+    let file = File::open(test.path)?;
+    let stream = TcpStream::connect(server.address)?;
+
+    let mut send_file = unsafe { send_file(file, stream) }
+        .with_headers(&[IoSlice::new(header)])
+        .with_trailers(&[IoSlice::new(trailer)]);
+
+    let result = Pin::new(&mut send_file).poll(&mut ctx)?;
+    assert!(result.is_ready());
+    assert_eq!(
+        send_file.written(),
+        header.len() + test.data.len() + trailer.len()
+    );
+
+    let (_, socket) = send_file.into_inner();
+    drop(socket); // Close the socket.
+
+    let (_, data) = server.send_files.recv().unwrap();
+    let mut expected = Vec::new();
+    expected.extend_from_slice(header);
+    expected.extend_from_slice(test.data);
+    expected.extend_from_slice(trailer);
+    assert_eq!(data, expected);
+
+    Ok(())
+}
+
+/// On Linux/Android `sendfile`'s destination historically had to be a
+/// socket; `send_file_to` falls back to `splice(2)` through a pipe for
+/// destinations (like a pipe itself) that aren't.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[test]
+fn pipe_non_blocking_splice_fallback() -> io::Result<()> {
+    use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+    use send_file::send_file_to;
+
+    /// The write end of a pipe, closed on drop.
+    struct PipeWriter(RawFd);
+
+    impl AsRawFd for PipeWriter {
+        fn as_raw_fd(&self) -> RawFd {
+            self.0
+        }
+    }
+
+    impl Drop for PipeWriter {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.0);
+            }
+        }
+    }
+
+    for test in TEST_FILES {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let (read_fd, write_end) = (fds[0], PipeWriter(fds[1]));
+
+        // Drain the pipe concurrently so writing the larger test files
+        // doesn't deadlock on the pipe's buffer capacity.
+        let reader = thread::spawn(move || {
+            let mut file = unsafe { File::from_raw_fd(read_fd) };
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf)
+                .expect("unable to read from pipe");
+            buf
+        });
+
+        let file = File::open(test.path)?;
+        let mut send_file = unsafe { send_file_to(file, write_end) };
+        wait_loop(Pin::new(&mut send_file))?;
+        assert_eq!(send_file.written(), test.data.len());
+        drop(send_file); // Close the write end so the reader sees EOF.
+
+        let received = reader.join().expect("reader thread panicked");
+        assert_eq!(received, test.data);
+    }
+
+    Ok(())
+}
+
 /// A simple wait loop that completes the future.
 fn wait_loop<Fut>(mut future: Pin<&mut Fut>) -> Fut::Output
 where